@@ -1,5 +1,5 @@
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     os::unix::prelude::OsStrExt,
     path::{Path, PathBuf},
 };
@@ -10,7 +10,7 @@ use license::{Exception, License};
 use once_cell::sync::Lazy;
 use regex::{bytes::Regex as BytesRegex, Regex};
 use serde::ser::SerializeMap;
-use spdx::{Expression, LicenseReq, ParseMode};
+use spdx::{ExprNode, Expression, LicenseReq, Operator, ParseMode};
 use std::fmt::Write;
 
 /// Commandline arguments
@@ -39,6 +39,19 @@ static CONFIG: Lazy<Config> = Lazy::new(|| {
     }
 });
 
+/// The SPDX license-list-data release pinned in `Config`, if any was configured and reachable.
+/// `None` means callers should fall back to the list compiled into the `license` crate.
+static SPDX_LIST: Lazy<Option<spdx_list::SpdxList>> = Lazy::new(|| {
+    let version = CONFIG.spdx_list_version.as_ref()?;
+    match spdx_list::fetch(version) {
+        Ok(list) => Some(list),
+        Err(e) => {
+            log::warn!("Could not fetch SPDX license list {version}, falling back to the embedded list: {e:?}");
+            None
+        }
+    }
+});
+
 #[derive(serde::Deserialize, Default, Debug)]
 struct Config {
     /// The licenses that are allowed
@@ -46,6 +59,227 @@ struct Config {
     allowed_licenses: Vec<LicenseReq>,
     #[serde(default)]
     ignore_packages: Vec<String>,
+    /// Per-package overrides for packages whose metadata is missing or wrong, keyed by package
+    /// name.
+    #[serde(default)]
+    clarifications: HashMap<String, Vec<Clarification>>,
+    /// Whether a package with no license and no covering clarification should abort the run
+    /// (`true`) or just be recorded as [`LicenseInfo::Unknown`] and warned about (`false`,
+    /// the default).
+    #[serde(default)]
+    error_on_unknown_license: bool,
+    /// Pin the SPDX `license-list-data` release to fetch license/exception texts from, e.g.
+    /// `"v3.23"`. When unset, or when the fetch fails (e.g. offline), falls back to whatever
+    /// list is compiled into the `license` crate.
+    #[serde(default)]
+    spdx_list_version: Option<String>,
+    /// User-supplied handlebars template files for each output, overriding the built-in layout.
+    #[serde(default)]
+    templates: TemplatesConfig,
+    /// If set, also write one notice file per dependency into this directory (in addition to
+    /// the combined reports), named by package name and version.
+    #[serde(default)]
+    split_output_dir: Option<PathBuf>,
+    /// Whether to also write an SPDX SBOM document (`3rd-party-notices.spdx.json`).
+    #[serde(default)]
+    generate_sbom: bool,
+    /// License categories considered acceptable, in addition to anything already listed
+    /// individually in `allowed_licenses`.
+    #[serde(default)]
+    allowed_categories: Vec<LicenseCategory>,
+    /// License categories that are always rejected, even if `allowed_categories` would
+    /// otherwise permit them. A license listed in `allowed_licenses` is still let through.
+    #[serde(default)]
+    denied_categories: Vec<LicenseCategory>,
+}
+
+/// A coarse bucket for an SPDX license id, used to write policy in terms of "no copyleft"
+/// rather than enumerating every acceptable id by hand. Ordered from least to most likely to
+/// carry obligations; [`worst_category`] folds this ordering over a license expression's `AND`
+/// (most restrictive side wins) and `OR` (least restrictive side wins) structure.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum LicenseCategory {
+    PublicDomain,
+    Permissive,
+    WeakCopyleft,
+    Copyleft,
+    Proprietary,
+    /// The id isn't in the built-in mapping, so we don't know its obligations.
+    Unknown,
+}
+
+/// Built-in id -> category mapping seeded from the well-known SPDX classifications, so policy
+/// can be expressed by category without configuring every id by hand.
+static LICENSE_CATEGORIES: Lazy<HashMap<&'static str, LicenseCategory>> = Lazy::new(|| {
+    use LicenseCategory::*;
+    HashMap::from([
+        ("MIT", Permissive),
+        ("MIT-0", Permissive),
+        ("Apache-2.0", Permissive),
+        ("BSD-2-Clause", Permissive),
+        ("BSD-3-Clause", Permissive),
+        ("ISC", Permissive),
+        ("Zlib", Permissive),
+        ("BSL-1.0", Permissive),
+        ("Unlicense", PublicDomain),
+        ("CC0-1.0", PublicDomain),
+        ("MPL-2.0", WeakCopyleft),
+        ("LGPL-2.1", WeakCopyleft),
+        ("LGPL-2.1-only", WeakCopyleft),
+        ("LGPL-2.1-or-later", WeakCopyleft),
+        ("LGPL-3.0", WeakCopyleft),
+        ("LGPL-3.0-only", WeakCopyleft),
+        ("LGPL-3.0-or-later", WeakCopyleft),
+        ("EPL-2.0", WeakCopyleft),
+        ("GPL-2.0", Copyleft),
+        ("GPL-2.0-only", Copyleft),
+        ("GPL-2.0-or-later", Copyleft),
+        ("GPL-3.0", Copyleft),
+        ("GPL-3.0-only", Copyleft),
+        ("GPL-3.0-or-later", Copyleft),
+        ("AGPL-3.0", Copyleft),
+        ("AGPL-3.0-only", Copyleft),
+        ("AGPL-3.0-or-later", Copyleft),
+    ])
+});
+
+/// Look up the category for an SPDX license id, defaulting to [`LicenseCategory::Unknown`] for
+/// ids not in the built-in mapping.
+fn license_category(id: &str) -> LicenseCategory {
+    LICENSE_CATEGORIES
+        .get(id)
+        .copied()
+        .unwrap_or(LicenseCategory::Unknown)
+}
+
+/// The effective category of a license expression. Unlike a flat `max` over every requirement,
+/// this respects the expression's structure: an `AND` is as restrictive as its most restrictive
+/// side (every branch's obligations apply), but an `OR` is only as restrictive as its *least*
+/// restrictive side, since the consumer can choose to satisfy that branch instead — so
+/// `MIT OR GPL-3.0` is `Permissive`, not `Copyleft`.
+fn worst_category(expr: &Expression) -> LicenseCategory {
+    let mut stack: Vec<LicenseCategory> = Vec::new();
+    for node in expr.iter() {
+        match node {
+            ExprNode::Req(req) => stack.push(
+                req.req
+                    .license
+                    .id()
+                    .map(|id| license_category(id.name))
+                    .unwrap_or(LicenseCategory::Unknown),
+            ),
+            ExprNode::Op(Operator::And) => {
+                let rhs = stack.pop().unwrap_or(LicenseCategory::Unknown);
+                let lhs = stack.pop().unwrap_or(LicenseCategory::Unknown);
+                stack.push(lhs.max(rhs));
+            }
+            ExprNode::Op(Operator::Or) => {
+                let rhs = stack.pop().unwrap_or(LicenseCategory::Unknown);
+                let lhs = stack.pop().unwrap_or(LicenseCategory::Unknown);
+                stack.push(lhs.min(rhs));
+            }
+        }
+    }
+    stack.pop().unwrap_or(LicenseCategory::Unknown)
+}
+
+/// Whether `req` is allowed: either it's listed verbatim in `allowed_licenses`, or its category
+/// is in `allowed_categories` and not in `denied_categories`. An explicit `allowed_licenses`
+/// entry always wins over a category denial.
+fn license_allowed(req: &LicenseReq) -> bool {
+    if CONFIG.allowed_licenses.iter().any(|x| x == req) {
+        return true;
+    }
+    let Some(id) = req.license.id() else {
+        return false;
+    };
+    let category = license_category(id.name);
+    if CONFIG.denied_categories.contains(&category) {
+        return false;
+    }
+    CONFIG.allowed_categories.contains(&category)
+}
+
+/// Paths to user-supplied handlebars templates, one per output format. Any left unset use the
+/// tool's built-in layout for that format.
+#[derive(serde::Deserialize, Default, Debug)]
+struct TemplatesConfig {
+    #[serde(default)]
+    html: Option<PathBuf>,
+    #[serde(default)]
+    markdown: Option<PathBuf>,
+    #[serde(default)]
+    json: Option<PathBuf>,
+}
+
+/// A maintainer-supplied correction for a single package (optionally scoped to a version range),
+/// for when the package's own metadata is missing or can't be trusted.
+#[derive(serde::Deserialize, Debug)]
+struct Clarification {
+    /// Only apply this clarification to versions matching this requirement. Applies to every
+    /// version of the package when absent.
+    version: Option<semver::VersionReq>,
+    /// The SPDX expression to use instead of whatever (if anything) the package's metadata says.
+    license: Option<String>,
+    /// The expected sha256 hex digest of the package's license file, used to warn if upstream
+    /// changes the license out from under us.
+    license_file_hash: Option<String>,
+    /// The exact notice text to record for this package, overriding anything `scan_for_notices`
+    /// would have found on its own.
+    notice_text: Option<String>,
+}
+
+/// Find the clarification (if any) that applies to package `name` at `version`. A clarification
+/// scoped to a matching version requirement is preferred over one that applies to all versions.
+fn find_clarification<'c>(
+    name: &str,
+    version: Option<&semver::Version>,
+) -> Option<&'c Clarification> {
+    let entries = CONFIG.clarifications.get(name)?;
+    select_clarification(entries, version)
+}
+
+/// The version-matching logic behind [`find_clarification`], split out so it can be tested
+/// without a [`CONFIG`].
+fn select_clarification<'c>(
+    entries: &'c [Clarification],
+    version: Option<&semver::Version>,
+) -> Option<&'c Clarification> {
+    entries
+        .iter()
+        .find(|c| match (&c.version, version) {
+            (Some(req), Some(v)) => req.matches(v),
+            _ => false,
+        })
+        .or_else(|| entries.iter().find(|c| c.version.is_none()))
+}
+
+/// Warn if none of the package's candidate license files hash to `expected_hash`.
+fn verify_license_file_hash(package_path: &Path, name: &str, expected_hash: &str) {
+    use sha2::{Digest, Sha256};
+
+    let Ok(dir) = std::fs::read_dir(package_path) else {
+        return;
+    };
+    for entry in dir.flatten() {
+        if !entry.file_type().map(|t| t.is_file()).unwrap_or(false)
+            || !COPYRIGHT_FILE_RE.is_match(entry.file_name().as_bytes())
+        {
+            continue;
+        }
+        if let Ok(contents) = std::fs::read(entry.path()) {
+            let hash = format!("{:x}", Sha256::digest(&contents));
+            if hash.eq_ignore_ascii_case(expected_hash) {
+                return;
+            }
+        }
+    }
+    log::warn!(
+        "Clarification for `{name}` pins a license file hash that doesn't match any file found \
+         in the package; the license file may have changed upstream"
+    );
 }
 
 fn license_reqs<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Vec<LicenseReq>, D::Error> {
@@ -79,12 +313,298 @@ impl<'de> serde::de::Visitor<'de> for LicenseReqVisitor {
     }
 }
 
-#[derive(Clone, Debug, Default, serde::Serialize)]
+#[derive(Clone, Debug, serde::Serialize)]
 pub struct Dep {
     name: String,
+    version: String,
     package_url: String,
-    license_id: String,
+    license_info: LicenseInfo,
+    /// The effective [`LicenseCategory`] of `license_info`, as computed by [`worst_category`].
+    category: LicenseCategory,
     notices: HashSet<String>,
+    /// One match per declared license requirement (so a dual-licensed crate keeps both its
+    /// `LICENSE-MIT` and `LICENSE-APACHE` texts, say), or a single guessed match if the license
+    /// was unknown. See [`match_license_text`].
+    license_matches: Vec<LicenseMatch>,
+}
+
+/// The license situation for a single dependency: either a parsed SPDX expression, or a marker
+/// for when one couldn't be determined.
+#[derive(Clone, Debug)]
+pub enum LicenseInfo {
+    /// A successfully parsed SPDX license expression.
+    Expr(Expression),
+    /// The package doesn't declare a license and no [`Clarification`] covers it.
+    Unknown,
+    /// A clarification explicitly opted this package out of license tracking.
+    Ignore,
+}
+
+impl std::fmt::Display for LicenseInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LicenseInfo::Expr(expr) => write!(f, "{expr}"),
+            LicenseInfo::Unknown => write!(f, "UNKNOWN"),
+            LicenseInfo::Ignore => write!(f, "IGNORED"),
+        }
+    }
+}
+
+impl serde::Serialize for LicenseInfo {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// How confident [`match_license_text`] is that a file it scanned contains the text of a
+/// particular SPDX license.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub enum Confidence {
+    /// The candidate file's word frequencies are a near-perfect match for the license template.
+    Confident,
+    /// The candidate file looks like the license, but differs enough that it's worth a human
+    /// double-checking ( e.g. extra boilerplate, a modified license ).
+    SemiConfident,
+    /// The candidate file doesn't look enough like the declared license to trust.
+    Unsure,
+    /// No file matching [`COPYRIGHT_FILE_RE`] was found in the package, or there was no license
+    /// text to match against (the requirement has no SPDX id, or its text isn't known).
+    #[default]
+    MissingLicenseFile,
+    /// More than one file looked like an equally good candidate, so we can't tell which one is
+    /// the actual license text.
+    MultiplePossibleLicenseFiles,
+}
+
+/// At or above this normalized error the match is considered [`Confidence::Unsure`].
+const SEMI_CONFIDENT_THRESHOLD: f64 = 0.15;
+/// Below this normalized error the match is considered [`Confidence::Confident`].
+const CONFIDENT_THRESHOLD: f64 = 0.10;
+/// If the two best-scoring candidate files are within this margin of each other, we can't tell
+/// them apart and report [`Confidence::MultiplePossibleLicenseFiles`] instead of guessing.
+const AMBIGUOUS_MARGIN: f64 = 0.02;
+
+/// Regex used to tokenize license text for word-frequency comparison.
+static WORD_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\w+").unwrap());
+
+/// Build a `word -> count` frequency table for a block of text, lowercased.
+fn word_frequencies(text: &str) -> HashMap<String, u32> {
+    let mut freqs = HashMap::new();
+    for word in WORD_RE.find_iter(text) {
+        *freqs.entry(word.as_str().to_lowercase()).or_insert(0) += 1;
+    }
+    freqs
+}
+
+/// Score how different `candidate`'s word frequencies are from `template`'s, normalized by the
+/// template's total word count. Lower is a better match; missing words count as zero.
+fn score_against_template(
+    candidate: &HashMap<String, u32>,
+    template: &HashMap<String, u32>,
+) -> f64 {
+    let mut error = 0u32;
+    let mut template_total = 0u32;
+    for (word, &template_count) in template {
+        let candidate_count = candidate.get(word).copied().unwrap_or(0);
+        error += template_count.abs_diff(candidate_count);
+        template_total += template_count;
+    }
+    if template_total == 0 {
+        return f64::MAX;
+    }
+    error as f64 / template_total as f64
+}
+
+/// The `(id, word-frequency table)` for every license known to the pinned [`SPDX_LIST`], or the
+/// built-in [`LICENSE_CATEGORIES`] ids if the list isn't available, used to guess the license of
+/// an [`LicenseInfo::Unknown`] package from its scanned files. Computed once and reused for every
+/// such package, rather than re-tokenizing the whole list (hundreds of licenses) on every call.
+static KNOWN_LICENSE_TEMPLATES: Lazy<Vec<(String, HashMap<String, u32>)>> = Lazy::new(|| {
+    match SPDX_LIST.as_ref() {
+        Some(list) => list
+            .all_licenses()
+            .map(|(id, text)| (id.to_string(), word_frequencies(text)))
+            .collect(),
+        None => LICENSE_CATEGORIES
+            .keys()
+            .filter_map(|id| Some((id.to_string(), id.parse::<&dyn License>().ok()?.text())))
+            .map(|(id, text)| (id, word_frequencies(text)))
+            .collect(),
+    }
+});
+
+/// The best-scoring `(id, score)` in [`KNOWN_LICENSE_TEMPLATES`] for a scanned file's word
+/// frequencies.
+fn best_known_license(freqs: &HashMap<String, u32>) -> Option<(&str, f64)> {
+    KNOWN_LICENSE_TEMPLATES
+        .iter()
+        .map(|(id, template)| (id.as_str(), score_against_template(freqs, template)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+/// Look up a license's canonical text, preferring the fetched [`SPDX_LIST`]'s copy (which can
+/// cover ids newer than whatever's compiled in) and falling back to the `license` crate.
+fn spdx_license_text(id: &str) -> Option<String> {
+    SPDX_LIST
+        .as_ref()
+        .and_then(|list| list.license_text(id))
+        .map(str::to_string)
+        .or_else(|| {
+            id.parse::<&dyn License>()
+                .ok()
+                .map(|license| license.text().to_string())
+        })
+}
+
+/// One license's best-matching scanned file, if any, and how confident the match is.
+#[derive(Clone, Debug, serde::Serialize)]
+struct LicenseMatch {
+    /// The SPDX id this is a match for: a declared requirement's own id, or `None` if the
+    /// requirement has no id (e.g. a `LicenseRef-*`). For an unknown license this is the guessed
+    /// id, or `None` if nothing could be guessed.
+    id: Option<String>,
+    /// The best-matching license text found in the package, if a single file stood out. This is
+    /// set whatever the `confidence`, including [`Confidence::Unsure`].
+    text: Option<String>,
+    /// How confident we are that `text` is actually `id`'s license.
+    confidence: Confidence,
+}
+
+/// Read every file in `path` matching [`COPYRIGHT_FILE_RE`], alongside its word frequencies so
+/// scoring it against multiple templates doesn't re-tokenize it each time.
+fn candidate_license_files(path: &Path) -> Vec<(String, HashMap<String, u32>)> {
+    let Ok(dir) = std::fs::read_dir(path) else {
+        return Vec::new();
+    };
+    dir.flatten()
+        .filter(|entry| {
+            entry.file_type().map(|t| t.is_file()).unwrap_or(false)
+                && COPYRIGHT_FILE_RE.is_match(entry.file_name().as_bytes())
+        })
+        .filter_map(|entry| std::fs::read_to_string(entry.path()).ok())
+        .map(|text| {
+            let freqs = word_frequencies(&text);
+            (text, freqs)
+        })
+        .collect()
+}
+
+/// Pick the best-matching file in `candidates` for a single `template` (one declared license
+/// requirement). "Ambiguous" means two *different* candidate files both look like a good match
+/// for *this* template — not that a good match for this template happens to also be close to a
+/// different template's best match (e.g. a dual `MIT OR Apache-2.0` crate's `LICENSE-MIT` and
+/// `LICENSE-APACHE` files should each cleanly match their own requirement, not collide).
+fn best_match_for_template(
+    candidates: &[(String, HashMap<String, u32>)],
+    template: &HashMap<String, u32>,
+) -> (Option<String>, Confidence) {
+    let mut scored: Vec<(&String, f64)> = candidates
+        .iter()
+        .map(|(text, freqs)| (text, score_against_template(freqs, template)))
+        .collect();
+    scored.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    match scored.as_slice() {
+        [] => (None, Confidence::MissingLicenseFile),
+        [(text, score)] => (Some((*text).clone()), confidence_for_score(*score)),
+        [(text, score), (_, next_score), ..] => {
+            if next_score - score < AMBIGUOUS_MARGIN {
+                (None, Confidence::MultiplePossibleLicenseFiles)
+            } else {
+                (Some((*text).clone()), confidence_for_score(*score))
+            }
+        }
+    }
+}
+
+/// Guess which known license an [`LicenseInfo::Unknown`] package's scanned files actually are, by
+/// scoring every candidate file against every template in [`KNOWN_LICENSE_TEMPLATES`] and keeping
+/// the file/id pair with the lowest error, so the guessed id can be reported even though it can't
+/// be used to upgrade `license_info`/`category` (that still requires a declared or clarified
+/// license). Ambiguous only if two *different* candidate files are both close contenders for
+/// being the real license file — not just one file scoring similarly against two near-identical
+/// license texts, e.g. `BSD-2-Clause` vs `BSD-3-Clause`.
+fn best_guess(candidates: &[(String, HashMap<String, u32>)]) -> LicenseMatch {
+    let mut scored: Vec<(&str, &str, f64)> = candidates
+        .iter()
+        .filter_map(|(text, freqs)| {
+            let (id, score) = best_known_license(freqs)?;
+            Some((id, text.as_str(), score))
+        })
+        .collect();
+    scored.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+    match scored.as_slice() {
+        [] => LicenseMatch {
+            id: None,
+            text: None,
+            confidence: Confidence::MissingLicenseFile,
+        },
+        [(id, text, score), rest @ ..] => {
+            let ambiguous = rest.iter().any(|(_, next_text, next_score)| {
+                next_score - score < AMBIGUOUS_MARGIN && next_text != text
+            });
+            if ambiguous {
+                LicenseMatch {
+                    id: None,
+                    text: None,
+                    confidence: Confidence::MultiplePossibleLicenseFiles,
+                }
+            } else {
+                LicenseMatch {
+                    id: Some(id.to_string()),
+                    text: Some(text.to_string()),
+                    confidence: confidence_for_score(*score),
+                }
+            }
+        }
+    }
+}
+
+/// Scan `path` for files matching [`COPYRIGHT_FILE_RE`] and match them against `reqs`, the
+/// package's declared license requirements, returning one [`LicenseMatch`] per requirement so a
+/// dual-licensed crate's `LICENSE-MIT`/`LICENSE-APACHE` files are each scored against their own
+/// requirement instead of colliding into one "ambiguous" slot. If `reqs` is empty (the package's
+/// license is unknown), guess the license from the scanned files via [`best_guess`] instead,
+/// returning a single inferred match.
+fn match_license_text(path: &Path, reqs: &[LicenseReq]) -> Vec<LicenseMatch> {
+    let candidates = candidate_license_files(path);
+
+    if reqs.is_empty() {
+        return vec![best_guess(&candidates)];
+    }
+
+    reqs.iter()
+        .map(|req| {
+            let id = req.license.id().map(|id| id.name.to_string());
+            let template = id
+                .as_deref()
+                .and_then(spdx_license_text)
+                .map(|text| word_frequencies(&text));
+            let (text, confidence) = match template {
+                Some(template) => best_match_for_template(&candidates, &template),
+                None => (None, Confidence::MissingLicenseFile),
+            };
+            LicenseMatch {
+                id,
+                text,
+                confidence,
+            }
+        })
+        .collect()
+}
+
+fn confidence_for_score(score: f64) -> Confidence {
+    if score < CONFIDENT_THRESHOLD {
+        Confidence::Confident
+    } else if score < SEMI_CONFIDENT_THRESHOLD {
+        Confidence::SemiConfident
+    } else {
+        Confidence::Unsure
+    }
 }
 
 #[derive(Default)]
@@ -98,12 +618,18 @@ impl serde::Serialize for Notices {
     where
         S: serde::Serializer,
     {
-        let mut map = serializer.serialize_map(Some(2))?;
+        let mut map = serializer.serialize_map(Some(3))?;
 
         let licenses = self.get_license_texts();
 
         map.serialize_entry("dependencies", &self.dependencies)?;
         map.serialize_entry("licenses", &licenses)?;
+        map.serialize_entry(
+            "spdx_list_version",
+            &SPDX_LIST
+                .as_ref()
+                .map(|list| format!("{} ({})", list.license_list_version, list.release_date)),
+        )?;
 
         map.end()
     }
@@ -137,20 +663,28 @@ impl Notices {
             .iter()
             .map(|x| {
                 (x.to_string(), {
+                    // `LicenseRef-*`/`DocumentRef-*` requirements (accepted by `ParseMode::LAX`,
+                    // e.g. from a clarification's custom `license` string) have no SPDX id, so
+                    // there's no text to look up for them.
                     let mut license_text = x
                         .license
                         .id()
-                        .unwrap()
-                        .name
-                        .parse::<&dyn License>()
-                        .unwrap()
-                        .text()
-                        .to_string();
-                    if let Some(exception) = x
-                        .exception
-                        .map(|x| x.name.parse::<&dyn Exception>().unwrap().text())
-                    {
-                        write!(license_text, "\n\nWITH EXCEPTION:\n\n{exception}").ok();
+                        .and_then(|id| spdx_license_text(id.name))
+                        .unwrap_or_else(|| format!("NOASSERTION ({})", x.license));
+                    if let Some(exception) = x.exception {
+                        let exception_text = SPDX_LIST
+                            .as_ref()
+                            .and_then(|list| list.exception_text(exception.name))
+                            .map(str::to_string)
+                            .or_else(|| {
+                                exception
+                                    .name
+                                    .parse::<&dyn Exception>()
+                                    .ok()
+                                    .map(|exception| exception.text().to_string())
+                            })
+                            .unwrap_or_else(|| format!("NOASSERTION ({})", exception.name));
+                        write!(license_text, "\n\nWITH EXCEPTION:\n\n{exception_text}").ok();
                     }
                     license_text
                 })
@@ -181,21 +715,48 @@ fn start() -> anyhow::Result<()> {
     cargo::collect_notices(&mut notices).context("Collecting cargo notices failed")?;
     pnpm::collect_notices(&mut notices).context("Collecting pnpm notices failed")?;
 
+    let unknown_count = notices
+        .dependencies
+        .iter()
+        .filter(|d| matches!(d.license_info, LicenseInfo::Unknown))
+        .count();
+    let ignored_count = notices
+        .dependencies
+        .iter()
+        .filter(|d| matches!(d.license_info, LicenseInfo::Ignore))
+        .count();
+    if unknown_count > 0 {
+        log::warn!("{unknown_count} package(s) have an unknown license");
+    }
+    if ignored_count > 0 {
+        log::info!("{ignored_count} package(s) were ignored via a clarification");
+    }
+
     // Create an HTML report
     {
-        let notices_html = generate::html(&notices);
+        let notices_html = generate::html(&notices).context("Generating HTML report failed")?;
         std::fs::write("3rd-party-notices.html", notices_html.as_bytes())?;
     }
     // Create a JSON report
     {
-        let notices_json = generate::json(&notices);
+        let notices_json = generate::json(&notices).context("Generating JSON report failed")?;
         std::fs::write("3rd-party-notices.json", notices_json.as_bytes())?;
     }
     // Create a Markdown report
     {
-        let notices_markdown = generate::markdown(&notices);
+        let notices_markdown =
+            generate::markdown(&notices).context("Generating Markdown report failed")?;
         std::fs::write("3rd-party-notices.md", notices_markdown.as_bytes())?;
     }
+    // Optionally write one notice file per dependency
+    if let Some(dir) = &CONFIG.split_output_dir {
+        generate::write_split(&notices, dir).context("Writing split notices failed")?;
+    }
+    // Optionally write an SPDX SBOM document
+    if CONFIG.generate_sbom {
+        let sbom = generate::spdx_sbom(&notices).context("Generating SPDX SBOM failed")?;
+        std::fs::write("3rd-party-notices.spdx.json", sbom.as_bytes())?;
+    }
 
     Ok(())
 }
@@ -242,16 +803,46 @@ fn scan_for_notices(out: &mut HashSet<String>, path: &Path) -> anyhow::Result<()
     Ok(())
 }
 
-fn handle_package_license(license: &str, notices: &mut Notices) -> anyhow::Result<()> {
-    // Get the package license
-    let license_expr = Expression::parse_mode(license, ParseMode::LAX)?;
+/// Resolve a package's license situation from its raw metadata string (already overridden by any
+/// [`Clarification`]). Returns an error if the license string doesn't parse, or if there is no
+/// license and the config says unknown licenses are a hard error.
+fn resolve_license_info(license: Option<String>, name: &str) -> anyhow::Result<LicenseInfo> {
+    match license {
+        Some(license) if license.eq_ignore_ascii_case("ignore") => Ok(LicenseInfo::Ignore),
+        Some(license) => Ok(LicenseInfo::Expr(Expression::parse_mode(
+            &license,
+            ParseMode::LAX,
+        )?)),
+        None if CONFIG.error_on_unknown_license => {
+            anyhow::bail!(
+                "Package {name} does not have a license and no clarification was configured for it"
+            )
+        }
+        None => {
+            log::warn!("Package {name} does not have a license; recording it as unknown");
+            Ok(LicenseInfo::Unknown)
+        }
+    }
+}
+
+/// Validate a package's license against `allowed_licenses`/`allowed_categories`, returning its
+/// requirements so the caller can register them with `Notices::add_license` and scan for license
+/// text. `Unknown`/`Ignore` packages have nothing to validate or register.
+///
+/// This does not touch `Notices` itself so that it can be called from parallel package-processing
+/// closures; requirements are merged into `Notices` by the caller once collection is done.
+fn handle_package_license(info: &LicenseInfo) -> anyhow::Result<Vec<LicenseReq>> {
+    let license_expr = match info {
+        LicenseInfo::Expr(expr) => expr,
+        LicenseInfo::Unknown | LicenseInfo::Ignore => return Ok(Vec::new()),
+    };
 
     // Validate license is allowed
     license_expr
-                .evaluate_with_failures(|req| CONFIG.allowed_licenses.iter().any(|x| x == req))
+                .evaluate_with_failures(license_allowed)
                 .map_err(|failed_licenses| {
                     let mut msg =
-                        String::from("None of the following licenses were allowed in the `allowed_licenses` configuration: ");
+                        String::from("None of the following licenses were allowed by the `allowed_licenses`/`allowed_categories` configuration: ");
                     let len = failed_licenses.len();
                     for (i, lic) in failed_licenses.into_iter().enumerate() {
                         write!(msg, "{}{}", lic.req, if i != len - 1 { ", " } else { "" }).ok();
@@ -259,84 +850,265 @@ fn handle_package_license(license: &str, notices: &mut Notices) -> anyhow::Resul
                     anyhow::format_err!(msg)
                 })?;
 
-    for req in license_expr.requirements() {
-        let req = req.req.clone();
-        notices.add_license(req);
+    Ok(license_expr.requirements().map(|x| x.req.clone()).collect())
+}
+
+/// Sort `notices.dependencies` by name and version so output is deterministic regardless of the
+/// order in which parallel package-processing closures finished.
+fn sort_dependencies(notices: &mut Notices) {
+    notices.dependencies.sort_by(|a, b| {
+        a.name.cmp(&b.name).then_with(|| {
+            match (
+                semver::Version::parse(&a.version),
+                semver::Version::parse(&b.version),
+            ) {
+                (Ok(a_version), Ok(b_version)) => a_version.cmp(&b_version),
+                // Not every ecosystem guarantees a semver-shaped version string; fall back to a
+                // lexical comparison rather than panicking or picking an arbitrary order.
+                _ => a.version.cmp(&b.version),
+            }
+        })
+    });
+}
+
+/// Fetches SPDX license/exception texts from a pinned `license-list-data` release, instead of
+/// relying on whatever version is compiled into the `license` crate.
+mod spdx_list {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    const LICENSES_URL: &str =
+        "https://raw.githubusercontent.com/spdx/license-list-data/{version}/json/licenses.json";
+    const EXCEPTIONS_URL: &str =
+        "https://raw.githubusercontent.com/spdx/license-list-data/{version}/json/exceptions.json";
+
+    #[derive(serde::Deserialize)]
+    struct LicensesJson {
+        #[serde(rename = "licenseListVersion")]
+        license_list_version: String,
+        #[serde(rename = "releaseDate")]
+        release_date: String,
+        licenses: Vec<LicenseEntry>,
     }
 
-    Ok(())
+    #[derive(serde::Deserialize)]
+    struct LicenseEntry {
+        #[serde(rename = "licenseId")]
+        license_id: String,
+        #[serde(rename = "licenseText")]
+        license_text: String,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct ExceptionsJson {
+        exceptions: Vec<ExceptionEntry>,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct ExceptionEntry {
+        #[serde(rename = "licenseExceptionId")]
+        license_exception_id: String,
+        #[serde(rename = "licenseExceptionText")]
+        license_exception_text: String,
+    }
+
+    /// A fetched SPDX `license-list-data` release: per-id license/exception texts, plus
+    /// provenance to record in the JSON report.
+    #[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+    pub struct SpdxList {
+        pub license_list_version: String,
+        pub release_date: String,
+        licenses: HashMap<String, String>,
+        exceptions: HashMap<String, String>,
+    }
+
+    impl SpdxList {
+        pub fn license_text(&self, id: &str) -> Option<&str> {
+            self.licenses.get(id).map(String::as_str)
+        }
+
+        pub fn exception_text(&self, id: &str) -> Option<&str> {
+            self.exceptions.get(id).map(String::as_str)
+        }
+
+        /// Every `(id, text)` pair in the list, for matching against an unidentified package.
+        pub fn all_licenses(&self) -> impl Iterator<Item = (&str, &str)> {
+            self.licenses.iter().map(|(id, text)| (id.as_str(), text.as_str()))
+        }
+    }
+
+    fn cache_path(version: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("make-notices-spdx-list-{version}.json"))
+    }
+
+    /// Fetch (or load from an on-disk cache) the SPDX `license-list-data` release tagged
+    /// `version`, e.g. `"v3.23"`.
+    pub fn fetch(version: &str) -> anyhow::Result<SpdxList> {
+        let cache_path = cache_path(version);
+        if let Ok(cached) = std::fs::read_to_string(&cache_path) {
+            if let Ok(list) = serde_json::from_str(&cached) {
+                return Ok(list);
+            }
+        }
+
+        let licenses_json: LicensesJson = ureq::get(&LICENSES_URL.replace("{version}", version))
+            .call()
+            .context("Downloading SPDX licenses.json failed")?
+            .into_json()
+            .context("Parsing SPDX licenses.json failed")?;
+        let exceptions_json: ExceptionsJson =
+            ureq::get(&EXCEPTIONS_URL.replace("{version}", version))
+                .call()
+                .context("Downloading SPDX exceptions.json failed")?
+                .into_json()
+                .context("Parsing SPDX exceptions.json failed")?;
+
+        let list = SpdxList {
+            license_list_version: licenses_json.license_list_version,
+            release_date: licenses_json.release_date,
+            licenses: licenses_json
+                .licenses
+                .into_iter()
+                .map(|l| (l.license_id, l.license_text))
+                .collect(),
+            exceptions: exceptions_json
+                .exceptions
+                .into_iter()
+                .map(|e| (e.license_exception_id, e.license_exception_text))
+                .collect(),
+        };
+
+        if let Ok(serialized) = serde_json::to_string(&list) {
+            std::fs::write(&cache_path, serialized).ok();
+        }
+
+        Ok(list)
+    }
 }
 
 mod cargo {
-    use cargo_metadata::MetadataCommand;
+    use cargo_metadata::{MetadataCommand, Package};
+    use rayon::prelude::*;
 
     use super::*;
 
-    pub fn collect_notices(notices: &mut Notices) -> anyhow::Result<()> {
-        let metadata = MetadataCommand::new()
-            .verbose(true)
-            .exec()
-            .context("Running `cargo metadata` failed")?;
+    /// Collect the `Dep` and license requirements for a single crate, reading package files only
+    /// under `package`'s own directory (the shared [`SPDX_LIST`] may also load or write its
+    /// on-disk cache on first use). Takes `package` by value and returns rather than
+    /// mutating a shared `Notices`, so `collect_notices` can run it across packages in parallel.
+    fn process_package(package: Package) -> anyhow::Result<(Dep, Vec<LicenseReq>)> {
+        let source = package.source.unwrap();
+        let name = package.name;
+        let version = package.version;
+        let clarification = find_clarification(&name, Some(&version));
+        let license = clarification
+            .and_then(|c| c.license.clone())
+            .or(package.license);
+
+        // Make sure the crate is from crates.io
+        let package_url = if source.is_crates_io() {
+            format!("https://crates.io/crates/{name}/{version}")
+        } else {
+            source.repr.clone()
+        };
 
-        // TODO: filter out build/dev dependencies and dependencies not associated to the desired
-        // target.
+        let package_path = package.manifest_path.parent().unwrap();
 
-        // Iterate over packages
-        let packages = metadata
-            .packages
-            .into_iter()
-            // Exclude local packages
-            .filter(|x| x.source.is_some());
-
-        for package in packages {
-            let source = package.source.unwrap();
-            let name = package.name;
-            if CONFIG.ignore_packages.contains(&name) {
-                continue;
-            }
-            let version = package.version;
-            let license = package
-                .license
-                .ok_or_else(|| anyhow::format_err!("Package {name} does not have a license"))?;
-
-            // Make sure the crate is from crates.io
-            let package_url = if source.is_crates_io() {
-                format!("https://crates.io/crates/{name}/{version}")
-            } else {
-                source.repr.clone()
-            };
+        if let Some(hash) = clarification.and_then(|c| c.license_file_hash.as_deref()) {
+            verify_license_file_hash(package_path.as_ref(), &name, hash);
+        }
 
-            handle_package_license(&license, notices)?;
+        let license_info = resolve_license_info(license, &name)?;
+        let category = match &license_info {
+            LicenseInfo::Expr(expr) => worst_category(expr),
+            LicenseInfo::Unknown | LicenseInfo::Ignore => LicenseCategory::Unknown,
+        };
+        let reqs = handle_package_license(&license_info)?;
 
-            // Scan the package for copyright notices
-            let dep_notices = {
-                let mut out = HashSet::default();
-                let package_path = package.manifest_path.parent().unwrap();
+        // Scan the package for copyright notices
+        let dep_notices = {
+            let mut out = HashSet::default();
 
-                // Add authors from the crate metadata
-                if !package.authors.is_empty() {
-                    out.insert(format!("Authors: {}", package.authors.join(", ")));
-                }
+            if let Some(notice_text) = clarification.and_then(|c| c.notice_text.clone()) {
+                out.insert(notice_text);
+            }
+
+            // Add authors from the crate metadata
+            if !package.authors.is_empty() {
+                out.insert(format!("Authors: {}", package.authors.join(", ")));
+            }
 
+            // A clarification opted this package out of license tracking entirely, so there's no
+            // point walking its directory for notices.
+            if !matches!(license_info, LicenseInfo::Ignore) {
                 scan_for_notices(&mut out, package_path.as_ref())?;
+            }
 
-                out
-            };
+            out
+        };
 
-            notices.dependencies.push(Dep {
+        // Likewise, don't scan for or infer license text for a package we're ignoring.
+        let license_matches = if matches!(license_info, LicenseInfo::Ignore) {
+            Vec::new()
+        } else {
+            match_license_text(package_path.as_ref(), &reqs)
+        };
+
+        Ok((
+            Dep {
                 package_url,
                 name,
-                license_id: license,
+                version: version.to_string(),
+                license_info,
+                category,
                 notices: dep_notices,
-            });
+                license_matches,
+            },
+            reqs,
+        ))
+    }
+
+    pub fn collect_notices(notices: &mut Notices) -> anyhow::Result<()> {
+        let metadata = MetadataCommand::new()
+            .verbose(true)
+            .exec()
+            .context("Running `cargo metadata` failed")?;
+
+        // TODO: filter out build/dev dependencies and dependencies not associated to the desired
+        // target.
+
+        // Iterate over packages, excluding local ones and any explicitly ignored in the config
+        let packages: Vec<_> = metadata
+            .packages
+            .into_iter()
+            .filter(|x| x.source.is_some())
+            .filter(|x| !CONFIG.ignore_packages.contains(&x.name))
+            .collect();
+
+        // Reading each package's manifest/license files and scanning for notices is independent
+        // per-package, so fan it out across a rayon thread pool; the results are merged into
+        // `notices` sequentially below to keep `add_license` dedup and output order deterministic.
+        let results: Vec<_> = packages.into_par_iter().map(process_package).collect();
+
+        for result in results {
+            let (dep, reqs) = result?;
+            for req in reqs {
+                notices.add_license(req);
+            }
+            notices.dependencies.push(dep);
         }
 
+        sort_dependencies(notices);
+
         Ok(())
     }
 }
 
 mod pnpm {
-    use std::{collections::HashMap, process::Command};
+    use std::process::Command;
+
+    use rayon::prelude::*;
 
     use super::*;
 
@@ -359,6 +1131,68 @@ mod pnpm {
         license: Option<String>,
     }
 
+    /// Collect the `Dep` and license requirements for the package at `path`, or `None` if it's in
+    /// `ignore_packages`. Returns rather than mutating a shared `Notices`, same as
+    /// `cargo::process_package`, so `collect_notices` can run it across packages in parallel.
+    fn process_package(path: PathBuf) -> anyhow::Result<Option<(Dep, Vec<LicenseReq>)>> {
+        let package_json_path = path.join("package.json");
+        let package: PackageJson = serde_json::from_reader(
+            std::fs::OpenOptions::new()
+                .read(true)
+                .open(package_json_path)?,
+        )?;
+        let name = package.name;
+        let version = package.version;
+
+        if CONFIG.ignore_packages.contains(&name) {
+            return Ok(None);
+        }
+
+        let parsed_version = semver::Version::parse(&version).ok();
+        let clarification = find_clarification(&name, parsed_version.as_ref());
+        let license = clarification
+            .and_then(|c| c.license.clone())
+            .or(package.license);
+
+        if let Some(hash) = clarification.and_then(|c| c.license_file_hash.as_deref()) {
+            verify_license_file_hash(&path, &name, hash);
+        }
+
+        let license_info = resolve_license_info(license, &name)?;
+        let category = match &license_info {
+            LicenseInfo::Expr(expr) => worst_category(expr),
+            LicenseInfo::Unknown | LicenseInfo::Ignore => LicenseCategory::Unknown,
+        };
+        let reqs = handle_package_license(&license_info)?;
+
+        let mut dep_notices = HashSet::default();
+        if let Some(notice_text) = clarification.and_then(|c| c.notice_text.clone()) {
+            dep_notices.insert(notice_text);
+        }
+
+        // A clarification opted this package out of license tracking entirely, so there's no
+        // point walking its directory for notices or scanning for/inferring license text.
+        let license_matches = if matches!(license_info, LicenseInfo::Ignore) {
+            Vec::new()
+        } else {
+            scan_for_notices(&mut dep_notices, &path)?;
+            match_license_text(&path, &reqs)
+        };
+
+        Ok(Some((
+            Dep {
+                package_url: format!("https://www.npmjs.com/package/{name}/v/{version}"),
+                name,
+                version,
+                license_info,
+                category,
+                notices: dep_notices,
+                license_matches,
+            },
+            reqs,
+        )))
+    }
+
     pub fn collect_notices(notices: &mut Notices) -> anyhow::Result<()> {
         if !Path::new("pnpm-lock.yaml").exists() {
             log::info!("Skipping pnpm packages because lockfile not found");
@@ -372,39 +1206,28 @@ mod pnpm {
         let pnpm_list: Vec<PnpmListItem> =
             serde_json::from_slice(&cmd_out.stdout).context("Error parsing pnpm list output")?;
 
-        for pkg in pnpm_list {
-            for (_, item) in pkg.dependencies.iter().chain(pkg.dev_dependencies.iter()) {
-                let package_json_path = item.path.join("package.json");
-                let package: PackageJson = serde_json::from_reader(
-                    std::fs::OpenOptions::new()
-                        .read(true)
-                        .open(package_json_path)?,
-                )?;
-                let name = package.name;
-                let version = package.version;
-
-                if CONFIG.ignore_packages.contains(&name) {
-                    continue;
+        let paths: Vec<PathBuf> = pnpm_list
+            .iter()
+            .flat_map(|pkg| pkg.dependencies.values().chain(pkg.dev_dependencies.values()))
+            .map(|item| item.path.clone())
+            .collect();
+
+        // Reading each package.json/license file and scanning for notices is independent
+        // per-package, so fan it out across a rayon thread pool; the results are merged into
+        // `notices` sequentially below to keep `add_license` dedup and output order deterministic.
+        let results: Vec<_> = paths.into_par_iter().map(process_package).collect();
+
+        for result in results {
+            if let Some((dep, reqs)) = result? {
+                for req in reqs {
+                    notices.add_license(req);
                 }
-
-                let license = package
-                    .license
-                    .ok_or_else(|| anyhow::format_err!("Package {name} doesn't have a license"))?;
-
-                handle_package_license(&license, notices)?;
-
-                let mut dep_notices = HashSet::default();
-                scan_for_notices(&mut dep_notices, &item.path)?;
-
-                notices.dependencies.push(Dep {
-                    package_url: format!("https://www.npmjs.com/package/{name}/v/{version}"),
-                    name,
-                    license_id: license,
-                    notices: dep_notices,
-                });
+                notices.dependencies.push(dep);
             }
         }
 
+        sort_dependencies(notices);
+
         Ok(())
     }
 }
@@ -412,7 +1235,225 @@ mod pnpm {
 mod generate {
     use super::*;
 
-    pub fn html(notices: &Notices) -> String {
+    /// Render the HTML report, using the user-supplied template from `Config` when configured,
+    /// falling back to the built-in layout otherwise.
+    pub fn html(notices: &Notices) -> anyhow::Result<String> {
+        match &CONFIG.templates.html {
+            Some(path) => render_template(path, notices),
+            None => Ok(default_html(notices)),
+        }
+    }
+
+    /// Render the Markdown report, using the user-supplied template from `Config` when
+    /// configured, falling back to the built-in layout otherwise.
+    pub fn markdown(notices: &Notices) -> anyhow::Result<String> {
+        match &CONFIG.templates.markdown {
+            Some(path) => render_template(path, notices),
+            None => Ok(default_markdown(notices)),
+        }
+    }
+
+    /// Render the JSON report, using the user-supplied template from `Config` when configured,
+    /// falling back to the plain serialization otherwise.
+    pub fn json(notices: &Notices) -> anyhow::Result<String> {
+        match &CONFIG.templates.json {
+            Some(path) => render_template(path, notices),
+            None => Ok(default_json(notices)),
+        }
+    }
+
+    /// Render `notices` through the handlebars template file at `path`.
+    fn render_template(path: &Path, notices: &Notices) -> anyhow::Result<String> {
+        let template = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read template file {path:?}"))?;
+        let mut hb = handlebars::Handlebars::new();
+        hb.register_template_string("report", template)
+            .with_context(|| format!("Could not parse template file {path:?}"))?;
+        let context = serde_json::to_value(notices)?;
+        hb.render("report", &context)
+            .with_context(|| format!("Could not render template file {path:?}"))
+    }
+
+    /// Write one notice file per dependency into `dir`, keyed by package name and version, in
+    /// addition to the combined report. Lets projects that vendor dependencies drop the right
+    /// NOTICE file next to each one.
+    pub fn write_split(notices: &Notices, dir: &Path) -> anyhow::Result<()> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Could not create split notices directory {dir:?}"))?;
+
+        for dep in &notices.dependencies {
+            let safe_name = dep.name.replace(['/', '\\'], "_");
+            let file_path = dir.join(format!("{safe_name}-{}-NOTICE.txt", dep.version));
+            std::fs::write(&file_path, split_notice_text(dep))
+                .with_context(|| format!("Could not write split notice file {file_path:?}"))?;
+        }
+
+        Ok(())
+    }
+
+    /// Build the combined notice text for a single dependency: its name/version/license, its
+    /// scanned notices, and the best license text we have for it.
+    fn split_notice_text(dep: &Dep) -> String {
+        let mut out = String::new();
+
+        writeln!(out, "{} {}", dep.name, dep.version).ok();
+        writeln!(out, "License: {} ({:?})", dep.license_info, dep.category).ok();
+        writeln!(out).ok();
+
+        for notice in &dep.notices {
+            writeln!(out, "{notice}").ok();
+            writeln!(out).ok();
+        }
+
+        // For each declared requirement (or the single guessed match for an unknown license),
+        // prefer the package's own scanned text when we trust it; otherwise fall back to the
+        // canonical text for a *declared* requirement only, since the id there is certain even
+        // when the scanned file isn't — a low-confidence guess isn't worth presuming about.
+        for license_match in &dep.license_matches {
+            if matches!(
+                license_match.confidence,
+                Confidence::Confident | Confidence::SemiConfident
+            ) {
+                if let Some(text) = &license_match.text {
+                    writeln!(out, "{text}").ok();
+                    continue;
+                }
+            }
+            if matches!(dep.license_info, LicenseInfo::Expr(_)) {
+                if let Some(text) = license_match.id.as_deref().and_then(spdx_license_text) {
+                    writeln!(out, "{text}").ok();
+                }
+            }
+        }
+
+        out
+    }
+
+    /// A single SPDX document, containing the root "project" package, one package per
+    /// dependency, and `DESCRIBES`/`CONTAINS` relationships tying them together.
+    #[derive(serde::Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct SpdxDocument {
+        spdx_version: String,
+        data_license: String,
+        #[serde(rename = "SPDXID")]
+        spdx_id: String,
+        name: String,
+        document_namespace: String,
+        creation_info: SpdxCreationInfo,
+        packages: Vec<SpdxPackage>,
+        relationships: Vec<SpdxRelationship>,
+    }
+
+    #[derive(serde::Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct SpdxCreationInfo {
+        created: String,
+        creators: Vec<String>,
+    }
+
+    #[derive(serde::Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct SpdxPackage {
+        #[serde(rename = "SPDXID")]
+        spdx_id: String,
+        name: String,
+        version_info: String,
+        download_location: String,
+        license_concluded: String,
+        license_declared: String,
+        copyright_text: String,
+    }
+
+    #[derive(serde::Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct SpdxRelationship {
+        spdx_element_id: String,
+        relationship_type: String,
+        related_spdx_element: String,
+    }
+
+    /// Replace any character the SPDX spec doesn't allow in an element id (only letters, digits,
+    /// `.` and `-`) with `-`.
+    fn spdx_ref_safe(s: &str) -> String {
+        s.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '-' })
+            .collect()
+    }
+
+    /// Turn the collected dependencies into an SPDX 2.3 JSON SBOM document.
+    pub fn spdx_sbom(notices: &Notices) -> anyhow::Result<String> {
+        const DOCUMENT_ID: &str = "SPDXRef-DOCUMENT";
+        const ROOT_PACKAGE_ID: &str = "SPDXRef-Package-root";
+
+        let created = chrono::Utc::now().to_rfc3339();
+
+        let mut packages = vec![SpdxPackage {
+            spdx_id: ROOT_PACKAGE_ID.to_string(),
+            name: "root".to_string(),
+            version_info: "NOASSERTION".to_string(),
+            download_location: "NOASSERTION".to_string(),
+            license_concluded: "NOASSERTION".to_string(),
+            license_declared: "NOASSERTION".to_string(),
+            copyright_text: "NOASSERTION".to_string(),
+        }];
+        let mut relationships = vec![SpdxRelationship {
+            spdx_element_id: DOCUMENT_ID.to_string(),
+            relationship_type: "DESCRIBES".to_string(),
+            related_spdx_element: ROOT_PACKAGE_ID.to_string(),
+        }];
+
+        for dep in &notices.dependencies {
+            let spdx_id = format!(
+                "SPDXRef-Package-{}",
+                spdx_ref_safe(&format!("{}-{}", dep.name, dep.version))
+            );
+            let license = match &dep.license_info {
+                LicenseInfo::Expr(expr) => expr.to_string(),
+                LicenseInfo::Unknown | LicenseInfo::Ignore => "NOASSERTION".to_string(),
+            };
+            let copyright_text = if dep.notices.is_empty() {
+                "NOASSERTION".to_string()
+            } else {
+                let mut notices: Vec<_> = dep.notices.iter().cloned().collect();
+                notices.sort();
+                notices.join("\n\n")
+            };
+
+            relationships.push(SpdxRelationship {
+                spdx_element_id: ROOT_PACKAGE_ID.to_string(),
+                relationship_type: "CONTAINS".to_string(),
+                related_spdx_element: spdx_id.clone(),
+            });
+            packages.push(SpdxPackage {
+                spdx_id,
+                name: dep.name.clone(),
+                version_info: dep.version.clone(),
+                download_location: dep.package_url.clone(),
+                license_concluded: license.clone(),
+                license_declared: license,
+                copyright_text,
+            });
+        }
+
+        let document = SpdxDocument {
+            spdx_version: "SPDX-2.3".to_string(),
+            data_license: "CC0-1.0".to_string(),
+            spdx_id: DOCUMENT_ID.to_string(),
+            name: "3rd Party Notices".to_string(),
+            document_namespace: format!("https://spdx.org/spdxdocs/make-notices-{created}"),
+            creation_info: SpdxCreationInfo {
+                created,
+                creators: vec!["Tool: make-notices".to_string()],
+            },
+            packages,
+            relationships,
+        };
+
+        Ok(serde_json::to_string_pretty(&document)?)
+    }
+
+    fn default_html(notices: &Notices) -> String {
         let mut out = String::new();
 
         writeln!(out, "<html>").ok();
@@ -463,7 +1504,7 @@ mod generate {
         out
     }
 
-    pub fn markdown(notices: &Notices) -> String {
+    fn default_markdown(notices: &Notices) -> String {
         let mut out = String::new();
 
         writeln!(out, "# 3rd Party Notices").ok();
@@ -481,7 +1522,7 @@ mod generate {
         out
     }
 
-    pub fn json(notices: &Notices) -> String {
+    fn default_json(notices: &Notices) -> String {
         serde_json::to_string_pretty(notices).unwrap()
     }
 
@@ -492,6 +1533,7 @@ mod generate {
         writeln!(out, "<th>Name</th>").ok();
         writeln!(out, "<th>Package URL</th>").ok();
         writeln!(out, "<th>License ID</th>").ok();
+        writeln!(out, "<th>Category</th>").ok();
         writeln!(out, "<th>Notices</th>").ok();
         writeln!(out, "</tr>").ok();
         writeln!(out, "</thead>").ok();
@@ -499,9 +1541,12 @@ mod generate {
         writeln!(out, "<tbody>").ok();
         for Dep {
             name,
+            version: _,
             package_url,
-            license_id,
+            license_info,
+            category,
             notices,
+            license_matches,
         } in deps
         {
             let notices_escaped = html_escape::encode_text(
@@ -511,13 +1556,178 @@ mod generate {
             writeln!(out, "<tr>").ok();
             writeln!(
                 out,
-                "<td>{name}</td><td><a href=\"{package_url}\">{package_url}</a></td><td>{license_id}</td><td>{notices_escaped}</td>"
+                "<td>{name}</td><td><a href=\"{package_url}\">{package_url}</a></td><td>{license_info}</td><td>{category:?}</td><td>{notices_escaped}</td>"
             )
             .ok();
             writeln!(out, "</tr>").ok();
+
+            // If we found the package's actual license text with reasonable confidence, show it
+            // instead of relying solely on the canonical text in the appendix below. One row per
+            // confidently-matched requirement, so a dual-licensed crate gets both texts shown.
+            for license_match in license_matches {
+                if !matches!(
+                    license_match.confidence,
+                    Confidence::Confident | Confidence::SemiConfident
+                ) {
+                    continue;
+                }
+                if let Some(text) = &license_match.text {
+                    let text_escaped = html_escape::encode_text(text);
+                    writeln!(
+                        out,
+                        "<tr><td colspan=\"5\"><pre style=\"text-wrap:wrap\">\n{text_escaped}\n</pre></td></tr>"
+                    )
+                    .ok();
+                }
+            }
         }
         writeln!(out, "</tbody>").ok();
 
         writeln!(out, "</table>\n").ok();
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn spdx_ref_safe_keeps_allowed_characters() {
+            assert_eq!(spdx_ref_safe("serde-1.0.2"), "serde-1.0.2");
+        }
+
+        #[test]
+        fn spdx_ref_safe_replaces_everything_else() {
+            assert_eq!(spdx_ref_safe("@scope/pkg_name"), "-scope-pkg-name");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expr(s: &str) -> Expression {
+        Expression::parse_mode(s, ParseMode::LAX).unwrap()
+    }
+
+    #[test]
+    fn worst_category_takes_the_most_restrictive_side_of_an_and() {
+        // GPL-3.0 AND MIT is as restrictive as its GPL side, since both sides' obligations apply.
+        assert_eq!(worst_category(&expr("GPL-3.0 AND MIT")), LicenseCategory::Copyleft);
+    }
+
+    #[test]
+    fn worst_category_takes_the_least_restrictive_side_of_an_or() {
+        // MIT OR GPL-3.0 is only as restrictive as MIT, since a consumer can choose that branch.
+        assert_eq!(worst_category(&expr("MIT OR GPL-3.0")), LicenseCategory::Permissive);
+    }
+
+    #[test]
+    fn worst_category_handles_mixed_and_or_structure() {
+        // (MIT OR GPL-3.0) AND AGPL-3.0: the OR resolves to Permissive, but the AND with AGPL
+        // still pulls the whole expression up to Copyleft.
+        assert_eq!(
+            worst_category(&expr("(MIT OR GPL-3.0) AND AGPL-3.0")),
+            LicenseCategory::Copyleft
+        );
+    }
+
+    #[test]
+    fn score_against_template_is_zero_for_an_identical_candidate() {
+        let template = word_frequencies("the quick brown fox");
+        let candidate = word_frequencies("the quick brown fox");
+        assert_eq!(score_against_template(&candidate, &template), 0.0);
+    }
+
+    #[test]
+    fn score_against_template_penalizes_missing_words() {
+        let template = word_frequencies("the quick brown fox");
+        let candidate = word_frequencies("the quick");
+        assert!(score_against_template(&candidate, &template) > 0.0);
+    }
+
+    #[test]
+    fn score_against_template_is_max_for_an_empty_template() {
+        let template = HashMap::new();
+        let candidate = word_frequencies("anything at all");
+        assert_eq!(score_against_template(&candidate, &template), f64::MAX);
+    }
+
+    #[test]
+    fn confidence_for_score_buckets_by_threshold() {
+        assert_eq!(confidence_for_score(0.0), Confidence::Confident);
+        assert_eq!(confidence_for_score(CONFIDENT_THRESHOLD), Confidence::SemiConfident);
+        assert_eq!(confidence_for_score(SEMI_CONFIDENT_THRESHOLD), Confidence::Unsure);
+        assert_eq!(confidence_for_score(1.0), Confidence::Unsure);
+    }
+
+    fn clarification(version: Option<&str>) -> Clarification {
+        Clarification {
+            version: version.map(|v| v.parse().unwrap()),
+            license: None,
+            license_file_hash: None,
+            notice_text: None,
+        }
+    }
+
+    #[test]
+    fn select_clarification_prefers_a_matching_version_over_the_catchall() {
+        let entries = vec![clarification(None), clarification(Some("=1.0.0"))];
+        let version = semver::Version::parse("1.0.0").unwrap();
+        let found = select_clarification(&entries, Some(&version)).unwrap();
+        assert!(found.version.is_some());
+    }
+
+    #[test]
+    fn select_clarification_falls_back_to_the_catchall() {
+        let entries = vec![clarification(None), clarification(Some("=1.0.0"))];
+        let version = semver::Version::parse("2.0.0").unwrap();
+        let found = select_clarification(&entries, Some(&version)).unwrap();
+        assert!(found.version.is_none());
+    }
+
+    #[test]
+    fn select_clarification_none_when_nothing_matches() {
+        let entries = vec![clarification(Some("=1.0.0"))];
+        let version = semver::Version::parse("2.0.0").unwrap();
+        assert!(select_clarification(&entries, Some(&version)).is_none());
+    }
+
+    fn dep(name: &str, version: &str) -> Dep {
+        Dep {
+            name: name.to_string(),
+            version: version.to_string(),
+            package_url: String::new(),
+            license_info: LicenseInfo::Unknown,
+            category: LicenseCategory::Unknown,
+            notices: HashSet::new(),
+            license_matches: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn sort_dependencies_orders_by_name_then_semver() {
+        let mut notices = Notices {
+            dependencies: vec![dep("b", "1.0.0"), dep("a", "2.0.0"), dep("a", "1.0.0")],
+            licences: Vec::new(),
+        };
+        sort_dependencies(&mut notices);
+        let names_versions: Vec<_> = notices
+            .dependencies
+            .iter()
+            .map(|d| (d.name.as_str(), d.version.as_str()))
+            .collect();
+        assert_eq!(names_versions, [("a", "1.0.0"), ("a", "2.0.0"), ("b", "1.0.0")]);
+    }
+
+    #[test]
+    fn sort_dependencies_falls_back_to_lexical_order_for_non_semver_versions() {
+        let mut notices = Notices {
+            dependencies: vec![dep("a", "not-a-version-b"), dep("a", "not-a-version-a")],
+            licences: Vec::new(),
+        };
+        sort_dependencies(&mut notices);
+        let versions: Vec<_> = notices.dependencies.iter().map(|d| d.version.as_str()).collect();
+        assert_eq!(versions, ["not-a-version-a", "not-a-version-b"]);
+    }
 }